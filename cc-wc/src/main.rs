@@ -1,16 +1,28 @@
 use std::{
     error::Error,
+    ffi::OsStr,
     fs::{self, File, Metadata},
     io::{self, Read, Write},
-    path::PathBuf,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
 };
 
 use clap::Parser;
+use encoding_rs::Encoding;
+use flate2::read::MultiGzDecoder;
 use memchr::memchr_iter;
 
+/// The two magic bytes that open every gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 #[derive(Parser, Debug)]
 struct Args {
-    input: Option<PathBuf>,
+    input: Vec<PathBuf>,
 
     #[arg(short = 'c', long = "bytes")]
     print_bytes: bool,
@@ -23,107 +35,479 @@ struct Args {
 
     #[arg(short = 'w', long = "words")]
     print_words: bool,
+
+    #[arg(short = 'L', long = "max-line-length")]
+    print_max_line_length: bool,
+
+    /// Force gzip decompression, even if the input doesn't start with the gzip magic bytes.
+    #[arg(short = 'Z', long = "decompress")]
+    decompress: bool,
+
+    /// Input encoding (e.g. "latin1", "utf-16", "shift-jis") to transcode before counting.
+    /// Defaults to treating input as UTF-8.
+    #[arg(long = "encoding")]
+    encoding: Option<String>,
+
+    /// Read NUL-separated input paths from FILE instead of the command line ("-" for stdin).
+    /// A path that can't be read is reported and skipped rather than aborting the whole run,
+    /// so one stale entry in a large list doesn't lose the counts for everything else.
+    #[arg(long = "files0-from", value_name = "FILE", conflicts_with = "input")]
+    files0_from: Option<PathBuf>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+/// Counts produced by running the counter pipeline over a single input.
+struct Counts {
+    bytes: u64,
+    chars: u64,
+    lines: u64,
+    words: u64,
+    max_line_length: u64,
+}
 
-    // Set all flags to true if none are selected
-    let (print_bytes, print_chars, print_newlines, print_words) = {
-        if !(args.print_bytes || args.print_chars || args.print_lines || args.print_words) {
+/// Which counts the user asked to see. `print_counts` emits them in `wc`'s order: newlines,
+/// words, chars, bytes, max line length.
+#[derive(Clone, Copy)]
+struct Selection {
+    bytes: bool,
+    chars: bool,
+    newlines: bool,
+    words: bool,
+    max_line_length: bool,
+}
+
+impl Selection {
+    fn from_args(args: &Args) -> Self {
+        // Default to lines, words and bytes if none are selected. -L is never part of the default set,
+        // but it still counts as an explicit selector that suppresses the default trio.
+        let (bytes, chars, newlines, words) = if !(args.print_bytes
+            || args.print_chars
+            || args.print_lines
+            || args.print_words
+            || args.print_max_line_length)
+        {
             (true, false, true, true)
         } else {
             (args.print_bytes, args.print_chars, args.print_lines, args.print_words)
+        };
+
+        Self {
+            bytes,
+            chars,
+            newlines,
+            words,
+            max_line_length: args.print_max_line_length,
+        }
+    }
+
+    /// True when `-c`/`--bytes` is the only count asked for.
+    fn is_bytes_only(&self) -> bool {
+        self.bytes && !self.chars && !self.newlines && !self.words && !self.max_line_length
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let selection = Selection::from_args(&args);
+
+    let encoding = match &args.encoding {
+        None => None,
+        Some(label) => Some(Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unknown encoding '{label}'"))
+        })?),
+    };
+
+    let input_paths = match &args.files0_from {
+        Some(list_path) => read_files0_from(list_path)?,
+        None => args.input.clone(),
+    };
+
+    // Treat no positional inputs and no --files0-from as a single read from stdin
+    let labels: Vec<Option<&PathBuf>> = if args.files0_from.is_none() && input_paths.is_empty() {
+        vec![None]
+    } else {
+        input_paths.iter().map(Some).collect()
+    };
+
+    let raw_results = if args.files0_from.is_none() && input_paths.is_empty() {
+        vec![count_input(None, selection, args.decompress, encoding)]
+    } else {
+        count_files(&input_paths, selection, args.decompress, encoding)
+    };
+
+    // A file that can't be read shouldn't take down the whole run: report it and move on,
+    // the same way GNU wc does when one path among several is missing or unreadable.
+    let mut had_error = false;
+    let mut results: Vec<Counts> = Vec::with_capacity(raw_results.len());
+    let mut printed_labels: Vec<Option<&PathBuf>> = Vec::with_capacity(raw_results.len());
+    for (result, label) in raw_results.into_iter().zip(&labels) {
+        match result {
+            Ok(counts) => {
+                results.push(counts);
+                printed_labels.push(*label);
+            }
+            Err(err) => {
+                had_error = true;
+                let name = label.map(|path| path.display().to_string()).unwrap_or_else(|| "-".to_string());
+                eprintln!("cc-wc: {name}: {err}");
+            }
         }
+    }
+
+    let mut total = Counts {
+        bytes: 0,
+        chars: 0,
+        lines: 0,
+        words: 0,
+        max_line_length: 0,
+    };
+    for counts in &results {
+        total.bytes += counts.bytes;
+        total.chars += counts.chars;
+        total.lines += counts.lines;
+        total.words += counts.words;
+        total.max_line_length = total.max_line_length.max(counts.max_line_length);
+    }
+
+    let largest = results
+        .iter()
+        .chain(std::iter::once(&total))
+        .flat_map(|counts| {
+            [
+                counts.bytes,
+                counts.chars,
+                counts.lines,
+                counts.words,
+                counts.max_line_length,
+            ]
+        })
+        .max()
+        .unwrap_or(0);
+    let output_width = (largest.max(1).ilog10() + 1) as usize;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+
+    for (counts, label) in results.iter().zip(&printed_labels) {
+        print_counts(
+            &mut handle,
+            counts,
+            label.map(|path| path.display().to_string()).as_deref(),
+            output_width,
+            selection,
+        )?;
+    }
+
+    // Whether to show a total is decided by how many paths were requested, not how many
+    // of them actually succeeded: GNU wc still prints a (possibly all-zero) total row
+    // when every file in a multi-file invocation failed.
+    if labels.len() > 1 {
+        print_counts(&mut handle, &total, Some("total"), output_width, selection)?;
+    }
+
+    handle.flush()?;
+    drop(handle);
+
+    if had_error {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Reads NUL-separated pathnames from `list_path` ("-" for stdin), mirroring GNU `wc
+/// --files0-from`. Empty entries (a trailing NUL, or none at all) are silently dropped.
+fn read_files0_from(list_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let contents = if list_path == Path::new("-") {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf)?;
+        buf
+    } else {
+        fs::read(list_path)?
     };
 
-    let (mut reader, metadata): (Box<dyn Read>, Option<Metadata>) = match &args.input {
-        None => {
-            // Read from stdin
-            (Box::new(io::stdin()), None)
+    Ok(contents
+        .split(|&byte| byte == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PathBuf::from(OsStr::from_bytes(entry)))
+        .collect())
+}
+
+/// Runs the counter pipeline over every file, keyed by its index in `paths`, using a pool of
+/// worker threads sized to the machine's available parallelism. Results come back in arbitrary
+/// completion order but are returned reordered to match `paths`.
+fn count_files(
+    paths: &[PathBuf],
+    selection: Selection,
+    decompress: bool,
+    encoding: Option<&'static Encoding>,
+) -> Vec<io::Result<Counts>> {
+    let pool_size = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(paths.len());
+
+    let next_index = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..pool_size {
+            let tx = tx.clone();
+            let next_index = &next_index;
+            scope.spawn(move || loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+                tx.send((index, count_input(Some(path), selection, decompress, encoding)))
+                    .unwrap();
+            });
         }
-        Some(path) => {
-            // Read from path
-            (Box::new(File::open(path)?), fs::metadata(path).ok())
+        drop(tx);
+    });
+
+    let mut results: Vec<Option<io::Result<Counts>>> = (0..paths.len()).map(|_| None).collect();
+    for (index, result) in rx {
+        results[index] = Some(result);
+    }
+    results.into_iter().map(|result| result.unwrap()).collect()
+}
+
+fn count_input(
+    label: Option<&PathBuf>,
+    selection: Selection,
+    decompress: bool,
+    encoding: Option<&'static Encoding>,
+) -> io::Result<Counts> {
+    let metadata = label.and_then(|path| fs::metadata(path).ok());
+
+    // Byte count on a regular file is already known from its metadata, so when that's all
+    // that was asked for, skip reading the file entirely and just stat it. A few bytes still
+    // have to be sniffed to rule out gzip content, whose metadata length is the compressed size.
+    if selection.is_bytes_only() && !decompress {
+        if let (Some(path), Some(data)) = (label, &metadata) {
+            if data.is_file() {
+                let mut file = File::open(path)?;
+                let mut magic = [0u8; 2];
+                let filled = fill_buffer(&mut file, &mut magic)?;
+                if filled < magic.len() || magic != GZIP_MAGIC {
+                    return Ok(Counts {
+                        bytes: data.len(),
+                        chars: 0,
+                        lines: 0,
+                        words: 0,
+                        max_line_length: 0,
+                    });
+                }
+                let mut reader = MultiGzDecoder::new(io::Cursor::new(magic).chain(file));
+                // Byte count doesn't need decoding, so the encoding is irrelevant here.
+                return run_counters(&mut reader, selection, label, &None, None);
+            }
         }
+    }
+
+    let raw_reader: Box<dyn Read> = match label {
+        None => Box::new(io::stdin()), // Read from stdin
+        Some(path) => Box::new(File::open(path)?),
     };
+    let (mut reader, is_gzip) = sniff_gzip(raw_reader, decompress)?;
+    // metadata.len() is the compressed size once decompression kicks in, so fall back to
+    // accumulating decompressed bytes as they're read instead of trusting it.
+    let byte_metadata = if is_gzip { None } else { metadata };
+
+    run_counters(&mut reader, selection, label, &byte_metadata, encoding)
+}
+
+/// Peeks the first two bytes of `reader` to detect the gzip magic, wrapping it in a streaming
+/// decoder when `force` is set or the magic is found. Returns the (possibly wrapped) reader
+/// along with whether decompression is active, with the peeked bytes replayed either way.
+fn sniff_gzip(mut reader: Box<dyn Read>, force: bool) -> io::Result<(Box<dyn Read>, bool)> {
+    let mut magic = [0u8; 2];
+    let filled = fill_buffer(&mut reader, &mut magic)?;
+    let is_gzip = force || (filled == magic.len() && magic == GZIP_MAGIC);
+    let prefixed: Box<dyn Read> = Box::new(io::Cursor::new(magic[..filled].to_vec()).chain(reader));
 
+    if is_gzip {
+        Ok((Box::new(MultiGzDecoder::new(prefixed)), true))
+    } else {
+        Ok((prefixed, false))
+    }
+}
+
+/// Fills `buf` as far as `reader` allows, stopping early at EOF. Returns the number of bytes read.
+fn fill_buffer(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn run_counters(
+    reader: &mut dyn Read,
+    selection: Selection,
+    label: Option<&PathBuf>,
+    metadata: &Option<Metadata>,
+    encoding: Option<&'static Encoding>,
+) -> io::Result<Counts> {
     // Initialize counters
-    let mut byte_counter = ByteCounter::new(&metadata);
+    let mut byte_counter = ByteCounter::new(metadata);
     let mut char_counter = CharCounter::new();
     let mut newline_counter = NewlineCounter::new();
     let mut word_counter = WordCounter::new();
+    let mut max_line_counter = MaxLineCounter::new();
+    let mut text_decoder = TextDecoder::new(encoding);
 
     // Read file in 16KB chunks
     let mut buffer = [0u8; 16384];
-    while let Ok(bytes_read) = reader.read(&mut buffer) {
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
         }
+        let chunk = &buffer[..bytes_read];
 
-        // Byte count
-        if print_bytes {
+        // Byte count always reflects the raw input, regardless of its encoding
+        if selection.bytes {
             byte_counter.count_bytes(bytes_read);
         }
 
-        // Char count
-        if print_chars {
-            char_counter.count_chars(&buffer[..bytes_read]);
-        }
-
-        // Line count
-        if print_newlines {
-            newline_counter.count_newlines(&buffer[..bytes_read]);
-        }
-
-        // Word count
-        if print_words {
-            word_counter.count_words(&buffer[..bytes_read]);
-        }
+        // Chars, newlines, words and line widths all operate on text decoded to UTF-8
+        let text = text_decoder.decode(chunk, false);
+        count_text(
+            text.as_bytes(),
+            selection,
+            &mut char_counter,
+            &mut newline_counter,
+            &mut word_counter,
+            &mut max_line_counter,
+        );
     }
 
+    // Flush any bytes the decoder buffered waiting for the rest of a multi-byte sequence
+    let final_text = text_decoder.decode(&[], true);
+    count_text(
+        final_text.as_bytes(),
+        selection,
+        &mut char_counter,
+        &mut newline_counter,
+        &mut word_counter,
+        &mut max_line_counter,
+    );
+
     if char_counter.invalid_chars_found || char_counter.remaining_bytes_in_char != 0 {
         let mut stderr_handle = io::stderr().lock();
         write!(stderr_handle, "Warning: Invalid UTF-8 detected")?;
-        if let Some(ref input) = args.input {
-            write!(stderr_handle, " in file {}", input.display())?;
+        if let Some(path) = label {
+            write!(stderr_handle, " in file {}", path.display())?;
         }
         writeln!(stderr_handle)?;
     }
 
-    let output_width = {
-        if args.print_bytes as u8 + args.print_chars as u8 + args.print_lines as u8 + args.print_words as u8 == 1 {
-            0 // Only one flag, no need for format
-        } else if let Some(ref metadata) = metadata {
-            (metadata.len().max(1).ilog10() + 1) as usize // byte count digits
-        } else {
-            7 // stdin, use default 7
+    Ok(Counts {
+        bytes: byte_counter.get(),
+        chars: char_counter.get(),
+        lines: newline_counter.get(),
+        words: word_counter.get(),
+        max_line_length: max_line_counter.get(),
+    })
+}
+
+fn count_text(
+    text: &[u8],
+    selection: Selection,
+    char_counter: &mut CharCounter,
+    newline_counter: &mut NewlineCounter,
+    word_counter: &mut WordCounter,
+    max_line_counter: &mut MaxLineCounter,
+) {
+    if selection.chars {
+        char_counter.count_chars(text);
+    }
+    if selection.newlines {
+        newline_counter.count_newlines(text);
+    }
+    if selection.words {
+        word_counter.count_words(text);
+    }
+    if selection.max_line_length {
+        max_line_counter.count_line_widths(text);
+    }
+}
+
+/// Transcodes chunks to UTF-8 for a configured `--encoding`, carrying decoder state across
+/// chunk boundaries so multi-byte sequences split across reads are handled correctly. Without
+/// an encoding, chunks are passed through unchanged (input is assumed to already be UTF-8).
+struct TextDecoder {
+    decoder: Option<encoding_rs::Decoder>,
+    buf: String,
+}
+impl TextDecoder {
+    fn new(encoding: Option<&'static Encoding>) -> Self {
+        Self {
+            decoder: encoding.map(Encoding::new_decoder),
+            buf: String::new(),
         }
-    };
+    }
+    fn decode<'c, 's>(&'s mut self, chunk: &'c [u8], last: bool) -> Decoded<'c, 's> {
+        match &mut self.decoder {
+            None => Decoded::Raw(chunk),
+            Some(decoder) => {
+                self.buf.clear();
+                self.buf
+                    .reserve(decoder.max_utf8_buffer_length(chunk.len()).unwrap_or(chunk.len()));
+                let _ = decoder.decode_to_string(chunk, &mut self.buf, last);
+                Decoded::Text(self.buf.as_bytes())
+            }
+        }
+    }
+}
 
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
+/// Either the original chunk (no `--encoding` set) or text freshly transcoded into the
+/// decoder's internal buffer.
+enum Decoded<'c, 's> {
+    Raw(&'c [u8]),
+    Text(&'s [u8]),
+}
+impl<'c, 's> Decoded<'c, 's> {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Decoded::Raw(chunk) => chunk,
+            Decoded::Text(text) => text,
+        }
+    }
+}
 
+fn print_counts(
+    handle: &mut impl Write,
+    counts: &Counts,
+    label: Option<&str>,
+    output_width: usize,
+    selection: Selection,
+) -> io::Result<()> {
     // In order: newline, word, character, byte, max line length
-    if print_newlines {
-        write!(handle, "{:>output_width$} ", newline_counter.get())?;
+    if selection.newlines {
+        write!(handle, "{:>output_width$} ", counts.lines)?;
     }
-    if print_words {
-        write!(handle, "{:>output_width$} ", word_counter.get())?;
+    if selection.words {
+        write!(handle, "{:>output_width$} ", counts.words)?;
     }
-    if print_chars {
-        write!(handle, "{:>output_width$} ", char_counter.get())?;
+    if selection.chars {
+        write!(handle, "{:>output_width$} ", counts.chars)?;
     }
-    if print_bytes {
-        write!(handle, "{:>output_width$} ", byte_counter.get())?;
+    if selection.bytes {
+        write!(handle, "{:>output_width$} ", counts.bytes)?;
     }
-    if let Some(ref input) = args.input {
-        write!(handle, "{}", input.display())?;
+    if selection.max_line_length {
+        write!(handle, "{:>output_width$} ", counts.max_line_length)?;
+    }
+    if let Some(label) = label {
+        write!(handle, "{}", label)?;
     }
 
-    writeln!(handle)?;
-
-    Ok(())
+    writeln!(handle)
 }
 
 struct ByteCounter {
@@ -242,3 +626,39 @@ impl WordCounter {
         self.word_count
     }
 }
+
+struct MaxLineCounter {
+    max_width: u64,
+    current_width: u64,
+}
+impl MaxLineCounter {
+    fn new() -> Self {
+        Self {
+            max_width: 0,
+            current_width: 0,
+        }
+    }
+    fn count_line_widths(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            match byte {
+                b'\n' => {
+                    self.max_width = self.max_width.max(self.current_width);
+                    self.current_width = 0;
+                }
+                b'\t' => {
+                    // Tabs jump to the next multiple of 8
+                    self.current_width = (self.current_width / 8 + 1) * 8;
+                }
+                // UTF-8 continuation bytes were already accounted for by their lead byte
+                byte if byte & 0xC0 == 0x80 => {}
+                // Other control characters contribute no width
+                byte if byte < 0x20 || byte == 0x7F => {}
+                _ => self.current_width += 1,
+            }
+        }
+    }
+    fn get(&self) -> u64 {
+        // A file not ending in a newline still has a line to account for
+        self.max_width.max(self.current_width)
+    }
+}